@@ -6,6 +6,11 @@ use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use spin::{Mutex, MutexGuard};
+
+/// Maximum symlink hops `find_resolved` follows before giving up, treating a
+/// longer chain as a loop.
+const MAX_SYMLINK_HOPS: usize = 40;
+
 /// Virtual filesystem layer over easy-fs
 pub struct Inode {
     /// Inode number
@@ -78,6 +83,22 @@ impl Inode {
             })
         })
     }
+    /// Like `find`, but if the looked-up entry is a symlink, follows its stored
+    /// target (resolved as another name under this same directory) instead of
+    /// returning the symlink inode itself. Bounds the number of hops at
+    /// `MAX_SYMLINK_HOPS` so a symlink cycle fails cleanly instead of looping.
+    pub fn find_resolved(&self, name: &str) -> Option<Arc<Inode>> {
+        let mut current = self.find(name)?;
+        for _ in 0..MAX_SYMLINK_HOPS {
+            if !current.is_symlink() {
+                return Some(current);
+            }
+            let target = current.read_link();
+            current = self.find(&target)?;
+        }
+        // the entry reached by the last allowed hop may itself be a plain entry
+        (!current.is_symlink()).then_some(current)
+    }
     /// Find inode under current inode by name
     pub fn list(&self) -> Vec<String> {
         self.read_disk_inode(|disk_inode| {
@@ -189,13 +210,14 @@ impl Inode {
         // block_cache_sync_all();
         0
     }
-    /// Get the stat of current inode
-    pub fn stat(&self) -> (usize, bool, bool, u32) {
+    /// Get the stat of current inode: `(ino, is_file, is_dir, is_symlink, link_count)`
+    pub fn stat(&self) -> (usize, bool, bool, bool, u32) {
         self.read_disk_inode(|disk_inode| {
             (
                 self.ino,
                 disk_inode.is_file(),
                 disk_inode.is_dir(),
+                disk_inode.is_symlink(),
                 disk_inode.link_count as u32,
             )
         })
@@ -324,4 +346,143 @@ impl Inode {
         });
         block_cache_sync_all();
     }
+    /// Current size in bytes of the data this inode holds
+    pub fn size(&self) -> usize {
+        self.read_disk_inode(|disk_inode| disk_inode.size as usize)
+    }
+    /// Whether this inode is a symlink.
+    pub fn is_symlink(&self) -> bool {
+        self.read_disk_inode(|disk_inode| disk_inode.is_symlink())
+    }
+    /// Create a symlink named `name` under the current (directory) inode, whose
+    /// data holds the literal `target` path (not resolved or validated at creation
+    /// time, matching how a target need not exist yet or may live elsewhere).
+    pub fn symlink(&self, target: &str, name: &str) -> Option<Arc<Inode>> {
+        let mut fs = self.fs.lock();
+        let op = |root_inode: &DiskInode| self.find_inode_id(name, root_inode);
+        if self.read_disk_inode(op).is_some() {
+            return None;
+        }
+        let new_inode_id = fs.alloc_inode();
+        let (new_inode_block_id, new_inode_block_offset) = fs.get_disk_inode_pos(new_inode_id);
+        get_block_cache(new_inode_block_id as usize, Arc::clone(&self.block_device))
+            .lock()
+            .modify(new_inode_block_offset, |new_inode: &mut DiskInode| {
+                new_inode.initialize(DiskInodeType::Symlink);
+            });
+        self.modify_disk_inode(|root_inode| {
+            let file_count = (root_inode.size as usize) / DIRENT_SZ;
+            let new_size = (file_count + 1) * DIRENT_SZ;
+            self.increase_size(new_size as u32, root_inode, &mut fs);
+            let dirent = DirEntry::new(name, new_inode_id);
+            root_inode.write_at(
+                file_count * DIRENT_SZ,
+                dirent.as_bytes(),
+                &self.block_device,
+            );
+        });
+        let (block_id, block_offset) = fs.get_disk_inode_pos(new_inode_id);
+        drop(fs); // `write_at` below takes the fs lock itself
+        let link_inode = Self::new(
+            new_inode_id as usize,
+            block_id,
+            block_offset,
+            self.fs.clone(),
+            self.block_device.clone(),
+        );
+        link_inode.write_at(0, target.as_bytes());
+        block_cache_sync_all();
+        Some(Arc::new(link_inode))
+    }
+    /// The target path stored in a symlink's data.
+    pub fn read_link(&self) -> String {
+        let mut buf = alloc::vec![0u8; self.size()];
+        self.read_at(0, &mut buf);
+        String::from_utf8_lossy(&buf).into_owned()
+    }
+}
+
+/// `whence` argument of [`FileHandle::lseek`], mirroring POSIX `SEEK_*`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SeekWhence {
+    /// Seek to an absolute offset
+    Set,
+    /// Seek relative to the current offset
+    Cur,
+    /// Seek relative to the end of the file
+    End,
+}
+
+/// Flag bit recognised by [`FileHandle`]; `O_APPEND` forces every write to first
+/// seek to the current end of file.
+pub const O_APPEND: u32 = 1 << 10;
+
+/// A seekable handle onto an [`Inode`].<br/>
+/// `Inode::read_at`/`write_at` only know absolute offsets, so every caller used to
+/// have to track a cursor itself; `FileHandle` carries that cursor (and the open
+/// flags) so it can back a POSIX-style `lseek`/`fcntl` interface at the syscall
+/// layer.
+pub struct FileHandle {
+    inode: Arc<Inode>,
+    offset: usize,
+    flags: u32,
+}
+
+impl FileHandle {
+    /// Open `inode` at offset 0 with the given open `flags`.
+    pub fn new(inode: Arc<Inode>, flags: u32) -> Self {
+        Self {
+            inode,
+            offset: 0,
+            flags,
+        }
+    }
+    /// Read into `buf` starting at the current offset, advancing it by however many
+    /// bytes were actually read.
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        let n = self.inode.read_at(self.offset, buf);
+        self.offset += n;
+        n
+    }
+    /// Write `buf` at the current offset (or at end-of-file first, if `O_APPEND` is
+    /// set), advancing the offset by however many bytes were actually written. A
+    /// write past the current end zero-fills the gap via `Inode::write_at`'s
+    /// existing `increase_size` path.
+    pub fn write(&mut self, buf: &[u8]) -> usize {
+        if self.flags & O_APPEND != 0 {
+            self.offset = self.inode.size();
+        }
+        let n = self.inode.write_at(self.offset, buf);
+        self.offset += n;
+        n
+    }
+    /// Reposition the cursor per `whence`, returning the new absolute offset.
+    /// Seeking past the end of the file is allowed; the gap is only materialized
+    /// (zero-filled) by a subsequent write.
+    pub fn lseek(&mut self, offset: isize, whence: SeekWhence) -> Option<usize> {
+        let base = match whence {
+            SeekWhence::Set => 0,
+            SeekWhence::Cur => self.offset as isize,
+            SeekWhence::End => self.inode.size() as isize,
+        };
+        let new_offset = base + offset;
+        if new_offset < 0 {
+            return None;
+        }
+        self.offset = new_offset as usize;
+        Some(self.offset)
+    }
+    /// Current open flags, as set at `new`/`set_flags` (`F_GETFL`).
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+    /// Overwrite the open flags (`F_SETFL`).
+    pub fn set_flags(&mut self, flags: u32) {
+        self.flags = flags;
+    }
+    /// The underlying inode, e.g. so a fd table can duplicate this handle onto
+    /// another descriptor (`F_DUPFD`) while sharing the same file.
+    pub fn inode(&self) -> Arc<Inode> {
+        self.inode.clone()
+    }
 }
\ No newline at end of file
@@ -20,4 +20,84 @@ pub enum UnMapError {
 }
 
 /// Result of unmapping
-pub type UnMapResult<T> = Result<T,UnMapError>;
\ No newline at end of file
+pub type UnMapResult<T> = Result<T,UnMapError>;
+
+/// The kind of memory access that triggered a page fault.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessKind {
+    /// A load (read) from the faulting address
+    Load,
+    /// A store (write) to the faulting address
+    Store,
+    /// An instruction fetch from the faulting address
+    Instr,
+}
+
+#[derive(Debug)]
+pub enum PageFaultError {
+    /// The faulting address is not covered by any `MapArea`
+    NoMatchingArea,
+    /// The access kind is incompatible with the area's `MapPermission`
+    ProtectionFault,
+    /// Lazily backing the faulting page failed (e.g. out of frames)
+    BackingFailed,
+}
+
+/// Result of handling a page fault
+pub type PageFaultResult<T> = Result<T,PageFaultError>;
+
+#[derive(Debug)]
+pub enum AreaError {
+    /// A requested page falls outside every registered `MapArea`
+    AreaRangeNotInclude,
+    /// No area starts exactly at the given address
+    NoMatchingArea,
+    /// The range touches a critical mapping (`TRAMPOLINE`/`TRAP_CONTEXT_BASE`)
+    AreaCritical,
+    /// `mmap` target range overlaps an already-mapped area
+    AreaHasMappedPortion,
+    /// `munmap` target range includes unmapped pages
+    AreaHasUnmappedPortion,
+    /// Requested `MapPermission` is simultaneously writable and executable
+    WriteExecConflict,
+}
+
+/// Umbrella error type for the `mm` module, so call sites that thread failures
+/// through several layers (allocation, mapping, translation) can use one `Result`.
+#[derive(Debug)]
+pub enum MMError {
+    Translate(TranslateError),
+    Map(MapError),
+    UnMap(UnMapError),
+    Area(AreaError),
+}
+
+impl From<TranslateError> for MMError {
+    fn from(e: TranslateError) -> Self {
+        MMError::Translate(e)
+    }
+}
+impl From<MapError> for MMError {
+    fn from(e: MapError) -> Self {
+        MMError::Map(e)
+    }
+}
+impl From<UnMapError> for MMError {
+    fn from(e: UnMapError) -> Self {
+        MMError::UnMap(e)
+    }
+}
+impl From<AreaError> for MMError {
+    fn from(e: AreaError) -> Self {
+        MMError::Area(e)
+    }
+}
+
+impl core::fmt::Display for MMError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Result type shared across the `mm` module
+pub type MMResult<T> = Result<T,MMError>;
\ No newline at end of file
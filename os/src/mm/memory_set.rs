@@ -1,17 +1,22 @@
 //! Implementation of [`MapArea`] and [`MemorySet`].
 
+use super::{frame_alloc, FrameTracker};
 use super::{MapArea, MapPermission, MapType};
 use super::{PTEFlags, PageTable, PageTableEntry};
-use super::{PhysAddr, VirtAddr, VirtPageNum};
+use super::{PhysAddr, PhysPageNum, VirtAddr, VirtPageNum};
 use super::VPNRange;
 use super::err::{AreaError, MMResult};
+use super::err::{AccessKind, PageFaultError, PageFaultResult};
 use crate::config::{
     KERNEL_STACK_SIZE, MEMORY_END, PAGE_SIZE, TRAMPOLINE, TRAP_CONTEXT_BASE, USER_STACK_SIZE,
 };
 use crate::sync::UPSafeCell;
+use alloc::collections::BTreeMap;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::arch::asm;
+use core::mem::MaybeUninit;
+use core::task::Poll;
 use lazy_static::*;
 use riscv::register::satp;
 
@@ -28,11 +33,119 @@ extern "C" {
     fn strampoline();
 }
 
+/// Backing store for a demand-paged `MapArea`: the slice `data[file_off..file_off+file_len]`
+/// holds the segment's on-disk contents (e.g. an ELF LOAD segment); any page beyond
+/// `file_len` within the area is pure BSS and is zero-filled on first touch.
+#[derive(Clone)]
+pub struct Backing {
+    pub data: Arc<[u8]>,
+    pub file_off: usize,
+    pub file_len: usize,
+}
+
+/// The Sv39 leaf granularity a `MapArea` maps its entries at. Mapping at level 1 or
+/// level 2 instead of level 0 sets R/W/X directly on a non-bottom PTE, trading one
+/// PTE for a whole 2 MiB/1 GiB range and cutting both page-table memory and TLB
+/// misses for large, naturally aligned mappings like the kernel image and the
+/// physical-memory window.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PageSize {
+    /// 4 KiB, Sv39 level-0 leaf
+    Normal,
+    /// 2 MiB, Sv39 level-1 leaf
+    Mega,
+    /// 1 GiB, Sv39 level-2 leaf
+    Giga,
+}
+
+const SZ_2M: usize = 1 << 21;
+const SZ_1G: usize = 1 << 30;
+
+/// Pick the largest page size `[start, end)` is naturally aligned to and large
+/// enough for. Only meaningful for `MapType::Identical` areas, where va == pa.
+fn choose_page_size(start: usize, end: usize) -> PageSize {
+    let len = end - start;
+    if start % SZ_1G == 0 && len >= SZ_1G {
+        PageSize::Giga
+    } else if start % SZ_2M == 0 && len >= SZ_2M {
+        PageSize::Mega
+    } else {
+        PageSize::Normal
+    }
+}
+
+/// Sv39 root PTE index used for the recursive self-map (see [`MemorySet::new_bare`]).
+const RECURSIVE_INDEX: usize = 511;
+
+/// Virtual address of the 4 KiB page that holds a page table, reached by walking
+/// the recursive self-map through root indexes `(i2, i1, i0)`, then picking out
+/// `entry` (an 8-byte PTE) within it.
+fn recursive_va(i2: usize, i1: usize, i0: usize, entry: usize) -> VirtAddr {
+    VirtAddr((i2 << 30) | (i1 << 21) | (i0 << 12) | (entry * 8))
+}
+
+impl MemorySet {
+    /// Recursive virtual address of `vpn`'s level-2 (root) PTE.
+    pub fn recursive_l2_pte_va(vpn: VirtPageNum) -> VirtAddr {
+        let idx = vpn.indexes();
+        recursive_va(RECURSIVE_INDEX, RECURSIVE_INDEX, RECURSIVE_INDEX, idx[0])
+    }
+    /// Recursive virtual address of `vpn`'s level-1 PTE.
+    pub fn recursive_l1_pte_va(vpn: VirtPageNum) -> VirtAddr {
+        let idx = vpn.indexes();
+        recursive_va(RECURSIVE_INDEX, RECURSIVE_INDEX, idx[0], idx[1])
+    }
+    /// Recursive virtual address of `vpn`'s level-0 (leaf) PTE.
+    pub fn recursive_l0_pte_va(vpn: VirtPageNum) -> VirtAddr {
+        let idx = vpn.indexes();
+        recursive_va(RECURSIVE_INDEX, idx[0], idx[1], idx[2])
+    }
+
+    /// Sv39 PTE mask of the writable flag (bit 2).
+    const PTE_WRITABLE_BIT: usize = 1 << 2;
+
+    /// Clear `vpn`'s writable bit in the *current* page table by writing straight
+    /// to its level-0 PTE through the recursive self-map, instead of re-walking
+    /// from the root the way a generic `PageTable` accessor would — this is the
+    /// cheap COW bit-flip the recursive mapping exists for. Caller must already
+    /// know `vpn` has a valid level-0 PTE (e.g. just obtained via `translate`).
+    unsafe fn clear_write_via_recursive_va(vpn: VirtPageNum) {
+        let pte_ptr = Self::recursive_l0_pte_va(vpn).0 as *mut usize;
+        *pte_ptr &= !Self::PTE_WRITABLE_BIT;
+    }
+}
+
 lazy_static! {
     /// The kernel's initial memory mapping(kernel address space)
     pub static ref KERNEL_SPACE: Arc<UPSafeCell<MemorySet>> =
         Arc::new(unsafe { UPSafeCell::new(MemorySet::new_kernel()) });
 }
+
+lazy_static! {
+    /// Global reference count for physical frames shared between address spaces
+    /// by copy-on-write `fork`. A frame absent from the table is treated as having
+    /// a single owner.
+    static ref COW_FRAME_REFS: UPSafeCell<BTreeMap<PhysPageNum, usize>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
+}
+
+/// Record one more COW owner of `ppn`.
+fn cow_frame_inc(ppn: PhysPageNum) {
+    *COW_FRAME_REFS.exclusive_access().entry(ppn).or_insert(1) += 1;
+}
+
+/// Drop one COW owner of `ppn`, returning the remaining count.
+fn cow_frame_dec(ppn: PhysPageNum) -> usize {
+    let mut refs = COW_FRAME_REFS.exclusive_access();
+    let count = refs.entry(ppn).or_insert(1);
+    *count -= 1;
+    *count
+}
+
+/// Current number of COW owners of `ppn` (1 if the frame isn't tracked).
+fn cow_frame_count(ppn: PhysPageNum) -> usize {
+    *COW_FRAME_REFS.exclusive_access().get(&ppn).unwrap_or(&1)
+}
 /// address space
 pub struct MemorySet {
     page_table: PageTable,
@@ -42,7 +155,12 @@ pub struct MemorySet {
 impl MemorySet {
     /// Create a new empty `MemorySet`.
     pub fn new_bare() -> MMResult<Self> {
-        let pt = PageTable::new()?;
+        let mut pt = PageTable::new()?;
+        // recursive self-map: root PTE 511 points back at the root table itself,
+        // non-leaf (R=W=X=0), so the kernel can walk/edit this address space's own
+        // page tables through ordinary loads/stores once it's active, without an
+        // identity map of all of physical memory
+        pt.map_recursive_self(RECURSIVE_INDEX);
         Ok(Self {
             page_table: pt,
             areas: Vec::new(),
@@ -52,6 +170,13 @@ impl MemorySet {
     pub fn token(&self) -> usize {
         self.page_table.token()
     }
+    /// Reject any permission that is simultaneously writable and executable (W^X).
+    fn check_wx(permission: MapPermission) -> MMResult<()> {
+        if permission.contains(MapPermission::W) && permission.contains(MapPermission::X) {
+            return Err(AreaError::WriteExecConflict.into());
+        }
+        Ok(())
+    }
     /// Insert framed area strictly
     pub fn insert_framed_area_strict(
         &mut self,
@@ -59,6 +184,7 @@ impl MemorySet {
         end_va: VirtAddr,
         permission: MapPermission,
     ) -> MMResult<()> {
+        Self::check_wx(permission)?;
         self.push_strict(
             MapArea::new(start_va, end_va, MapType::Framed, permission),
             None,
@@ -71,6 +197,7 @@ impl MemorySet {
         end_va: VirtAddr,
         permission: MapPermission,
     ) -> MMResult<()> {
+        Self::check_wx(permission)?;
         self.push_lazy(
             MapArea::new(start_va, end_va, MapType::Framed, permission),
             None,
@@ -128,51 +255,56 @@ impl MemorySet {
         );
         info!("mapping .text section");
         memory_set.push_lazy(
-            MapArea::new(
+            MapArea::new_with_page_size(
                 (stext as usize).into(),
                 (etext as usize).into(),
                 MapType::Identical,
                 MapPermission::R | MapPermission::X,
+                choose_page_size(stext as usize, etext as usize),
             ),
             None,
         ).unwrap();
         info!("mapping .rodata section");
         memory_set.push_lazy(
-            MapArea::new(
+            MapArea::new_with_page_size(
                 (srodata as usize).into(),
                 (erodata as usize).into(),
                 MapType::Identical,
                 MapPermission::R,
+                choose_page_size(srodata as usize, erodata as usize),
             ),
             None,
         ).unwrap();
         info!("mapping .data section");
         memory_set.push_lazy(
-            MapArea::new(
+            MapArea::new_with_page_size(
                 (sdata as usize).into(),
                 (edata as usize).into(),
                 MapType::Identical,
                 MapPermission::R | MapPermission::W,
+                choose_page_size(sdata as usize, edata as usize),
             ),
             None,
         ).unwrap();
         info!("mapping .bss section");
         memory_set.push_lazy(
-            MapArea::new(
+            MapArea::new_with_page_size(
                 (sbss_with_stack as usize).into(),
                 (ebss as usize).into(),
                 MapType::Identical,
                 MapPermission::R | MapPermission::W,
+                choose_page_size(sbss_with_stack as usize, ebss as usize),
             ),
             None,
         ).unwrap();
         info!("mapping physical memory");
         memory_set.push_lazy(
-            MapArea::new(
+            MapArea::new_with_page_size(
                 (ekernel as usize).into(),
                 MEMORY_END.into(),
                 MapType::Identical,
                 MapPermission::R | MapPermission::W,
+                choose_page_size(ekernel as usize, MEMORY_END),
             ),
             None,
         ).unwrap();
@@ -184,6 +316,9 @@ impl MemorySet {
         let mut memory_set = Self::new_bare()?;
         // map trampoline
         memory_set.map_trampoline()?;
+        // shared by every LOAD segment's backing descriptor, so demand-paging a
+        // segment never needs to copy the whole image up front
+        let elf_image: Arc<[u8]> = Arc::from(elf_data);
         // map program headers of elf, with U flag
         let elf = xmas_elf::ElfFile::new(elf_data).unwrap();
         let elf_header = elf.header;
@@ -207,14 +342,17 @@ impl MemorySet {
                 if ph_flags.is_execute() {
                     map_perm |= MapPermission::X;
                 }
-                let map_area = MapArea::new(start_va, end_va, MapType::Framed, map_perm);
+                Self::check_wx(map_perm)?;
+                let mut map_area = MapArea::new(start_va, end_va, MapType::Framed, map_perm);
                 max_end_vpn = map_area.get_range().get_end();
-                // loaded area should always be strict, as they don't require more than needed,
-                // and for now we have no way for lazy load.
-                memory_set.push_strict(
-                    map_area,
-                    Some(&elf.input[ph.offset() as usize..(ph.offset() + ph.file_size()) as usize]),
-                )?;
+                // demand-paged: frames are only allocated (and filled from the image)
+                // the first time a page in this segment actually faults in
+                map_area.set_backing(Some(Backing {
+                    data: elf_image.clone(),
+                    file_off: ph.offset() as usize,
+                    file_len: ph.file_size() as usize,
+                }));
+                memory_set.push_lazy(map_area, None)?;
             }
         }
         // map user stack with U flags
@@ -280,6 +418,100 @@ impl MemorySet {
         self.find_area_ensure(vpn)?;
         self.page_table.translate(vpn)
     }
+    /// Handle a trap-driven page fault on `vpn` caused by `access`.<br/>
+    /// Locates the `MapArea` covering `vpn`; a missing area or a permission mismatch
+    /// between `access` and the area's `MapPermission` (e.g. a store into an `R`-only
+    /// page) is reported so the trap handler can turn it into a fatal signal. Otherwise
+    /// the fault is on a permission-compatible but still-lazy page, so only that single
+    /// page is backed via `ensure_range` and the caller may resume the faulting instruction.
+    pub fn handle_page_fault(&mut self, vpn: VirtPageNum, access: AccessKind) -> PageFaultResult<()> {
+        let area = self
+            .areas
+            .iter_mut()
+            .find(|x| x.get_range().contains(&vpn))
+            .ok_or(PageFaultError::NoMatchingArea)?;
+        let perm = area.get_permission();
+        let compatible = match access {
+            AccessKind::Load => perm.contains(MapPermission::R),
+            AccessKind::Store => perm.contains(MapPermission::W),
+            AccessKind::Instr => perm.contains(MapPermission::X),
+        };
+        if !compatible {
+            return Err(PageFaultError::ProtectionFault);
+        }
+        if access == AccessKind::Store && area.is_cow(vpn) {
+            return Self::resolve_cow_fault(area, &mut self.page_table, vpn, perm);
+        }
+        if let Some(backing) = area.backing() {
+            return Self::resolve_backed_fault(area, &mut self.page_table, vpn, backing);
+        }
+        area.ensure_range(&mut self.page_table, VPNRange::by_len(vpn, 1))
+            .map_err(|_| PageFaultError::BackingFailed)
+    }
+
+    /// Resolve a fault on a page within a demand-paged, file-backed area: allocate
+    /// the frame, then copy whatever part of `backing` overlaps this page and
+    /// zero-fill the rest (the BSS tail, or the whole page past `file_len`).
+    fn resolve_backed_fault(
+        area: &mut MapArea,
+        page_table: &mut PageTable,
+        vpn: VirtPageNum,
+        backing: Backing,
+    ) -> PageFaultResult<()> {
+        // a page that is already present keeps its current contents
+        if page_table.translate(vpn).is_ok() {
+            return Ok(());
+        }
+        area.ensure_range(page_table, VPNRange::by_len(vpn, 1))
+            .map_err(|_| PageFaultError::BackingFailed)?;
+        let seg_start = area.get_range().get_start();
+        let page_idx = vpn.0 - seg_start.0;
+        let page_file_off = backing.file_off + page_idx * PAGE_SIZE;
+        let ppn = page_table
+            .translate(vpn)
+            .map_err(|_| PageFaultError::BackingFailed)?
+            .ppn();
+        let dst = ppn.get_bytes_array();
+        dst.fill(0);
+        let backing_end = backing.file_off + backing.file_len;
+        if page_file_off < backing_end {
+            let copy_len = (backing_end - page_file_off).min(PAGE_SIZE);
+            dst[..copy_len].copy_from_slice(&backing.data[page_file_off..page_file_off + copy_len]);
+        }
+        Ok(())
+    }
+
+    /// Resolve a store fault on a COW page: if the old frame is no longer shared, just
+    /// restore the `W` bit in place; otherwise allocate a fresh frame, copy the old
+    /// page's bytes into it, and remap the faulting entry before dropping our COW
+    /// reference on the old frame.
+    fn resolve_cow_fault(
+        area: &mut MapArea,
+        page_table: &mut PageTable,
+        vpn: VirtPageNum,
+        perm: MapPermission,
+    ) -> PageFaultResult<()> {
+        let old_ppn = page_table
+            .translate(vpn)
+            .map_err(|_| PageFaultError::NoMatchingArea)?
+            .ppn();
+        if cow_frame_count(old_ppn) <= 1 {
+            area.clear_cow(vpn);
+            return area
+                .restore_write(page_table, vpn)
+                .map_err(|_| PageFaultError::BackingFailed);
+        }
+        let frame: FrameTracker = frame_alloc().ok_or(PageFaultError::BackingFailed)?;
+        frame
+            .ppn
+            .get_bytes_array()
+            .copy_from_slice(old_ppn.get_bytes_array());
+        area.remap(page_table, vpn, frame, perm)
+            .map_err(|_| PageFaultError::BackingFailed)?;
+        area.clear_cow(vpn);
+        cow_frame_dec(old_ppn);
+        Ok(())
+    }
     /// shrink the area to new_end
     #[allow(unused)]
     pub fn shrink_to(&mut self, start: VirtAddr, new_end: VirtAddr) -> MMResult<()> {
@@ -343,6 +575,7 @@ impl MemorySet {
         end_va: VirtAddr,
         permission: MapPermission,
     ) -> MMResult<()>  {
+        Self::check_wx(permission)?;
         let area = MapArea::new(start_va, end_va, MapType::Framed, permission);
         if area.get_range().into_iter().any(|x|self.is_critical(x)) {
             return Err(AreaError::AreaCritical.into());
@@ -394,6 +627,216 @@ impl MemorySet {
         Ok(())
     }
 
+    /// Change the permission of an already fully-mapped `[start_va, end_va)` range.<br/>
+    /// Splits any `MapArea` straddling the range boundaries (reusing the `split` logic
+    /// `munmap` already relies on), rewrites the PTE flags of every currently-backed
+    /// page in the range, and flushes the TLB. Refuses to touch critical pages or to
+    /// leave a region both writable and executable.
+    pub fn mprotect(
+        &mut self,
+        start_va: VirtAddr,
+        end_va: VirtAddr,
+        perm: MapPermission,
+    ) -> MMResult<()> {
+        Self::check_wx(perm)?;
+        let target_range = VPNRange::new(start_va.floor(), end_va.ceil());
+        if target_range.into_iter().any(|x| self.is_critical(x)) {
+            return Err(AreaError::AreaCritical.into());
+        }
+        if self.has_unmapped(target_range) {
+            return Err(AreaError::AreaHasUnmappedPortion.into());
+        }
+        let areas = core::mem::take(&mut self.areas);
+        for area in areas.into_iter() {
+            let (l, _, rem) = area.get_range().exclude(&target_range);
+            if rem.is_empty() {
+                self.areas.push(area);
+                continue;
+            }
+            let (larea, rarea) = area.split(l.get_end());
+            let (mut marea, rarea) = rarea.split(rem.get_end());
+            if !larea.get_range().is_empty() {
+                self.areas.push(larea);
+            }
+            if !rarea.get_range().is_empty() {
+                self.areas.push(rarea);
+            }
+            for vpn in marea.get_range() {
+                if self.page_table.translate(vpn).is_ok() {
+                    marea.remap_flags(&mut self.page_table, vpn, perm)?;
+                }
+            }
+            marea.set_permission(perm);
+            self.areas.push(marea);
+        }
+        unsafe {
+            asm!("sfence.vma");
+        }
+        Ok(())
+    }
+
+    /// Read one word at `va`, for `PTRACE_PEEKDATA`.
+    pub fn ptrace_peek(&mut self, va: VirtAddr) -> MMResult<usize> {
+        let vpn = va.floor();
+        let offset = va.page_offset();
+        let ppn = self.translate(vpn)?.ppn();
+        let width = core::mem::size_of::<usize>();
+        let bytes = &ppn.get_bytes_array()[offset..offset + width];
+        Ok(usize::from_ne_bytes(bytes.try_into().unwrap()))
+    }
+
+    /// Write one word to `va`, for `PTRACE_POKEDATA`.
+    pub fn ptrace_poke(&mut self, va: VirtAddr, value: usize) -> MMResult<()> {
+        let vpn = va.floor();
+        let offset = va.page_offset();
+        let ppn = self.translate(vpn)?.ppn();
+        let width = core::mem::size_of::<usize>();
+        ppn.get_bytes_array()[offset..offset + width].copy_from_slice(&value.to_ne_bytes());
+        Ok(())
+    }
+
+    /// Clone this address space for `fork`, sharing writable `Framed` pages
+    /// copy-on-write instead of copying them up front.<br/>
+    /// Identical-mapped kernel areas and the critical `TRAMPOLINE`/`TRAP_CONTEXT_BASE`
+    /// pages are never shared: they're remapped eagerly in the child so neither process
+    /// can observe the other mutating kernel-owned state.
+    pub fn clone_cow(&mut self) -> MMResult<MemorySet> {
+        let mut child = Self::new_bare()?;
+        child.map_trampoline()?;
+        for area in self.areas.iter_mut() {
+            let eager = area.get_map_type() == MapType::Identical
+                || area.get_range().into_iter().any(|vpn| self.is_critical(vpn));
+            if eager {
+                let mut cloned = MapArea::from_another(area);
+                cloned.map(&mut child.page_table)?;
+                cloned.ensure_all(&mut child.page_table)?;
+                for vpn in area.get_range() {
+                    if let (Ok(src), Ok(dst)) = (
+                        self.page_table.translate(vpn),
+                        child.page_table.translate(vpn),
+                    ) {
+                        dst.ppn()
+                            .get_bytes_array()
+                            .copy_from_slice(src.ppn().get_bytes_array());
+                    }
+                }
+                child.areas.push(cloned);
+                continue;
+            }
+            let mut cloned = MapArea::from_another(area);
+            for vpn in area.get_range() {
+                let Ok(pte) = self.page_table.translate(vpn) else {
+                    continue;
+                };
+                if pte.writable() {
+                    // clear W in both parent and child, mark the page COW, and
+                    // share the frame until the next store fault splits it
+                    unsafe { Self::clear_write_via_recursive_va(vpn) };
+                    area.mark_cow(vpn);
+                }
+                // Whether this page is only becoming COW now or was already COW
+                // from an earlier fork, the child is a new owner of the shared
+                // frame: mark it COW in the child's own area and bump the shared
+                // refcount unconditionally, not just on the writable->COW
+                // transition, so a second fork of an already-COW'd page is
+                // counted too.
+                cloned.mark_cow(vpn);
+                cow_frame_inc(pte.ppn());
+                cloned.map_shared(&mut child.page_table, vpn, pte.ppn(), pte.flags() - PTEFlags::W)?;
+            }
+            child.areas.push(cloned);
+        }
+        Ok(child)
+    }
+
+}
+
+/// A page-sized, page-aligned scratch buffer for [`BlockCopier`].<br/>
+/// Alignment matters because the buffer is handed out as a byte slice that stands
+/// in for a physical page; keeping it page-aligned costs nothing and rules out any
+/// accidental cross-page slicing bugs.
+#[repr(align(4096))]
+pub struct AlignedBuf([MaybeUninit<u8>; PAGE_SIZE]);
+
+impl AlignedBuf {
+    fn new() -> Self {
+        // SAFETY: an array of `MaybeUninit<u8>` requires no initialization
+        Self(unsafe { MaybeUninit::uninit().assume_init() })
+    }
+}
+
+/// Resumable byte-for-byte copier between two address spaces (which may be the
+/// same one), so a large transfer can yield between page-sized chunks instead of
+/// hogging a core.<br/>
+/// A single chunk is clipped to whichever side's next page boundary is closer,
+/// since `src` and `dst` can straddle a page seam into *different* physical frames
+/// on either side.
+pub struct BlockCopier {
+    src: VirtAddr,
+    dst: VirtAddr,
+    count: usize,
+    buf: AlignedBuf,
+}
+
+impl BlockCopier {
+    /// Start copying `count` bytes from `src` to `dst`.
+    pub fn new(src: VirtAddr, dst: VirtAddr, count: usize) -> Self {
+        Self {
+            src,
+            dst,
+            count,
+            buf: AlignedBuf::new(),
+        }
+    }
+
+    /// Copy at most one buffer-sized chunk, faulting pages into `src_set`/`dst_set`
+    /// as needed. Returns `Poll::Pending` until `count` reaches zero, letting the
+    /// caller yield the CPU between chunks for large transfers.
+    pub fn poll(
+        &mut self,
+        src_set: &mut MemorySet,
+        dst_set: &mut MemorySet,
+    ) -> Poll<MMResult<()>> {
+        if self.count == 0 {
+            return Poll::Ready(Ok(()));
+        }
+        let src_room = PAGE_SIZE - self.src.page_offset();
+        let dst_room = PAGE_SIZE - self.dst.page_offset();
+        let chunk = self.count.min(PAGE_SIZE).min(src_room).min(dst_room);
+
+        let src_vpn = self.src.floor();
+        let _ = src_set.handle_page_fault(src_vpn, AccessKind::Load);
+        let src_pte = match src_set.page_table.translate(src_vpn) {
+            Ok(pte) => pte,
+            Err(_) => return Poll::Ready(Err(AreaError::AreaRangeNotInclude.into())),
+        };
+        let src_off = self.src.page_offset();
+        let src_bytes = &src_pte.ppn().get_bytes_array()[src_off..src_off + chunk];
+        // SAFETY: the first `chunk` bytes are about to be fully initialized below
+        let buf = unsafe {
+            core::slice::from_raw_parts_mut(self.buf.0.as_mut_ptr() as *mut u8, chunk)
+        };
+        buf.copy_from_slice(src_bytes);
+
+        let dst_vpn = self.dst.floor();
+        let _ = dst_set.handle_page_fault(dst_vpn, AccessKind::Store);
+        let dst_pte = match dst_set.page_table.translate(dst_vpn) {
+            Ok(pte) => pte,
+            Err(_) => return Poll::Ready(Err(AreaError::AreaRangeNotInclude.into())),
+        };
+        let dst_off = self.dst.page_offset();
+        dst_pte.ppn().get_bytes_array()[dst_off..dst_off + chunk].copy_from_slice(buf);
+
+        self.src = VirtAddr(self.src.0 + chunk);
+        self.dst = VirtAddr(self.dst.0 + chunk);
+        self.count -= chunk;
+
+        if self.count == 0 {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
 }
 
 /// Return (bottom, top) of a kernel stack in kernel space.
@@ -0,0 +1,64 @@
+//! Syscall dispatch: every `UserEnvCall` trap lands here with a syscall number
+//! and up to four argument registers, and leaves by returning the value to
+//! place in `a0`.
+mod process;
+
+use process::*;
+
+use crate::task::SeccompDecision;
+
+/// Linux-style `-EPERM`, returned to a task whose seccomp policy denies a syscall.
+const EPERM: isize = -1;
+
+const SYSCALL_OPEN: usize = 56;
+const SYSCALL_FCNTL: usize = 25;
+const SYSCALL_LSEEK: usize = 62;
+const SYSCALL_PTRACE: usize = 117;
+const SYSCALL_EXIT: usize = 93;
+const SYSCALL_YIELD: usize = 124;
+const SYSCALL_SET_PRIORITY: usize = 140;
+const SYSCALL_GET_TIME: usize = 169;
+const SYSCALL_GETPID: usize = 172;
+const SYSCALL_SBRK: usize = 214;
+const SYSCALL_MUNMAP: usize = 215;
+const SYSCALL_MPROTECT: usize = 226;
+const SYSCALL_FORK: usize = 220;
+const SYSCALL_EXEC: usize = 221;
+const SYSCALL_MMAP: usize = 222;
+const SYSCALL_SECCOMP: usize = 277;
+const SYSCALL_WAITPID: usize = 260;
+const SYSCALL_SPAWN: usize = 400;
+const SYSCALL_TASK_INFO: usize = 410;
+
+/// Dispatch syscall `id` with `args`, returning the value to place in `a0`.
+pub fn syscall(id: usize, args: [usize; 4]) -> isize {
+    // record_syscall kills the task itself on SeccompDecision::Kill, so by the
+    // time it returns here only Allow/Deny are left to act on: Deny must stop
+    // the syscall from actually running instead of just being a value nothing
+    // reads, which is all it was before this check existed.
+    if crate::task::record_syscall(id) == SeccompDecision::Deny {
+        return EPERM;
+    }
+    match id {
+        SYSCALL_OPEN => sys_open(args[0] as *const u8, args[1] as u32),
+        SYSCALL_FCNTL => sys_fcntl(args[0], args[1], args[2] as i32),
+        SYSCALL_LSEEK => sys_lseek(args[0], args[1] as isize, args[2]),
+        SYSCALL_PTRACE => sys_ptrace(args[0], args[1], args[2], args[3]),
+        SYSCALL_EXIT => sys_exit(args[0] as i32),
+        SYSCALL_YIELD => sys_yield(),
+        SYSCALL_SET_PRIORITY => sys_set_priority(args[0] as isize),
+        SYSCALL_GET_TIME => sys_get_time(args[0] as *mut TimeVal, args[1]),
+        SYSCALL_GETPID => sys_getpid(),
+        SYSCALL_SBRK => sys_sbrk(args[0] as i32),
+        SYSCALL_MUNMAP => sys_munmap(args[0], args[1]),
+        SYSCALL_MPROTECT => sys_mprotect(args[0], args[1], args[2]),
+        SYSCALL_FORK => sys_fork(),
+        SYSCALL_EXEC => sys_exec(args[0] as *const u8),
+        SYSCALL_MMAP => sys_mmap(args[0], args[1], args[2]),
+        SYSCALL_SECCOMP => sys_set_seccomp(args[0], args[1], args[2]),
+        SYSCALL_WAITPID => sys_waitpid(args[0] as isize, args[1] as *mut i32),
+        SYSCALL_SPAWN => sys_spawn(args[0] as *const u8),
+        SYSCALL_TASK_INFO => sys_task_info(args[0] as *mut TaskInfo),
+        _ => panic!("Unsupported syscall_id: {}", id),
+    }
+}
@@ -4,14 +4,19 @@ use alloc::sync::Arc;
 
 use crate::{
     config::MAX_SYSCALL_NUM,
-    fs::{open_file, OpenFlags},
-    mm::{translated_refmut, translated_str},
+    fs::{open_file, FileHandle, OpenFlags, SeekWhence},
+    mm::{translated_refmut, translated_str, VirtAddr},
+    sync::UPSafeCell,
     task::{
-        add_task, copy_to_user, current_task, current_user_token, exit_current_and_run_next,
-        get_current_task, mmap, munmap, suspend_current_and_run_next, TaskStatus,
+        add_task, copy_from_user, copy_to_user, current_task, current_user_token,
+        exit_current_and_run_next, get_current_task, inherit_seccomp, install_seccomp, mmap,
+        mprotect, munmap, suspend_current_and_run_next, SeccompAction, SeccompMode, TaskControlBlock,
+        TaskStatus,
     },
     timer::get_time_ms,
 };
+use alloc::collections::{BTreeMap, BTreeSet};
+use lazy_static::*;
 
 #[repr(C)]
 #[derive(Debug)]
@@ -58,6 +63,10 @@ pub fn sys_fork() -> isize {
     // we do not have to move to next instruction since we have done it before
     // for child process, fork returns 0
     trap_cx.x[10] = 0;
+    // a sandboxed process must not be able to shed its seccomp filter just by forking
+    inherit_seccomp(&current_task, &new_task);
+    // so ptrace can reach this child by pid
+    register_pid(&new_task);
     // add new task to scheduler
     add_task(new_task);
     new_pid as isize
@@ -77,8 +86,18 @@ pub fn sys_exec(path: *const u8) -> isize {
     }
 }
 
+/// Sentinel `*exit_code_ptr` value [`sys_waitpid`] writes when it is reporting a
+/// `ptrace`-stopped child rather than a reaped zombie, so the tracer can tell the
+/// two apart. A zombie child that exited with status `i32::MIN` reports the same
+/// value.
+const WAITPID_STOPPED_CODE: i32 = i32::MIN;
+
 /// If there is not a child process whose pid is same as given, return -1.
-/// Else if there is a child process but it is still running, return -2.
+/// Else if there is a child process but it is still running, return -2. A
+/// `ptrace`-stopped child (see `PTRACE_ATTACH`/`PTRACE_SINGLESTEP`) is reported
+/// the same way a zombie is, via [`WAITPID_STOPPED_CODE`], but is *not* reaped
+/// from `children` since it's still alive — the tracer is expected to loop on
+/// `sys_waitpid` until the tracee actually exits.
 pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
     trace!(
         "kernel::pid[{}] sys_waitpid [{}]",
@@ -113,6 +132,15 @@ pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
         // ++++ release child PCB
         *translated_refmut(inner.memory_set.token(), exit_code_ptr) = exit_code;
         found_pid as isize
+    } else if let Some(stopped) = inner.children.iter().find(|p| {
+        // ++++ temporarily access child PCB exclusively
+        p.inner_exclusive_access().task_status == TaskStatus::Stopped
+            && (pid == -1 || pid as usize == p.getpid())
+        // ++++ release child PCB
+    }) {
+        let found_pid = stopped.getpid();
+        *translated_refmut(inner.memory_set.token(), exit_code_ptr) = WAITPID_STOPPED_CODE;
+        found_pid as isize
     } else {
         -2
     }
@@ -129,16 +157,13 @@ pub fn sys_get_time(_ts: *mut TimeVal, _tz: usize) -> isize {
         sec: time_ms / 1000,
         usec: (time_ms % 1000) * 1000,
     };
-    unsafe {
-        copy_to_user(
-            _ts as usize,
-            core::slice::from_raw_parts(
-                &tv as *const _ as *const u8,
-                core::mem::size_of::<TimeVal>(),
-            ),
-        );
+    let bytes = unsafe {
+        core::slice::from_raw_parts(&tv as *const _ as *const u8, core::mem::size_of::<TimeVal>())
+    };
+    match copy_to_user(_ts as usize, bytes) {
+        Ok(_) => 0,
+        Err(_) => -1,
     }
-    0
 }
 
 /// YOUR JOB: Finish sys_task_info to pass testcases
@@ -155,16 +180,16 @@ pub fn sys_task_info(_ti: *mut TaskInfo) -> isize {
         syscall_times: current_info.1,
         time: get_time_ms() - current_info.2,
     };
-    unsafe {
-        copy_to_user(
-            _ti as usize,
-            core::slice::from_raw_parts(
-                &task_info as *const _ as *const u8,
-                core::mem::size_of::<TaskInfo>(),
-            ),
-        );
+    let bytes = unsafe {
+        core::slice::from_raw_parts(
+            &task_info as *const _ as *const u8,
+            core::mem::size_of::<TaskInfo>(),
+        )
+    };
+    match copy_to_user(_ti as usize, bytes) {
+        Ok(_) => 0,
+        Err(_) => -1,
     }
-    0
 }
 
 /// YOUR JOB: Implement mmap.
@@ -204,6 +229,230 @@ pub fn sys_munmap(_start: usize, _len: usize) -> isize {
     munmap(_start, _len)
 }
 
+/// `mprotect(start, len, prot)`: change the protection of an already-mapped
+/// `[start, start + len)` range. Same `start` alignment and `prot` high-bit checks
+/// as `sys_mmap`; unlike `sys_mmap`, a `prot` of `0` is accepted.
+pub fn sys_mprotect(_start: usize, _len: usize, _prot: usize) -> isize {
+    trace!("kernel:pid[{}] sys_mprotect", current_task().unwrap().pid.0);
+
+    // start 没有按页大小对齐
+    if _start & 0xfff != 0 {
+        return -1;
+    }
+
+    // port & !0x7 != 0 (port 其余位必须为0)
+    if _prot & !0x7 != 0 {
+        return -1;
+    }
+
+    mprotect(_start, _len, _prot)
+}
+
+const PTRACE_TRACEME: usize = 0;
+const PTRACE_PEEKDATA: usize = 2;
+const PTRACE_ATTACH: usize = 3;
+const PTRACE_DETACH: usize = 4;
+const PTRACE_POKEDATA: usize = 5;
+const PTRACE_CONT: usize = 7;
+const PTRACE_SINGLESTEP: usize = 9;
+const PTRACE_GETREGS: usize = 12;
+const PTRACE_SETREGS: usize = 13;
+
+lazy_static! {
+    /// pid -> task registry, so `ptrace` requests can reach an arbitrary
+    /// tracee's TCB instead of only the calling task's own. Populated in
+    /// `sys_fork`/`sys_spawn`; entries are never removed, the same tradeoff
+    /// `inner.children` already makes for a zombie's TCB.
+    static ref PID_TABLE: UPSafeCell<BTreeMap<usize, Arc<TaskControlBlock>>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
+}
+
+/// Record `task` under its own pid, so a later `ptrace` call naming that pid
+/// can reach it.
+fn register_pid(task: &Arc<TaskControlBlock>) {
+    PID_TABLE
+        .exclusive_access()
+        .insert(task.pid.0, Arc::clone(task));
+}
+
+/// Resolve a `ptrace` target pid to its TCB: the calling task itself needs no
+/// registry lookup (and is reachable even if it was never `register_pid`'d,
+/// e.g. the very first task), anything else goes through `PID_TABLE`.
+fn resolve_pid(pid: usize) -> Option<Arc<TaskControlBlock>> {
+    let current = current_task().unwrap();
+    if current.pid.0 == pid {
+        return Some(current);
+    }
+    PID_TABLE.exclusive_access().get(&pid).cloned()
+}
+
+/// A `ptrace(2)`-style syscall: `(request, pid, addr, data)`, matching the
+/// real `ptrace(2)` argument order so every request (`ATTACH`/`DETACH`/`CONT`/
+/// `SINGLESTEP`/`GETREGS`/`SETREGS`/`PEEKDATA`/`POKEDATA`) can target any
+/// known tracee via `pid`, not just the calling task. `TRACEME` ignores `pid`
+/// (it only ever makes sense for the tracee to call on itself).
+pub fn sys_ptrace(request: usize, pid: usize, addr: usize, data: usize) -> isize {
+    trace!("kernel:pid[{}] sys_ptrace", current_task().unwrap().pid.0);
+    if request == PTRACE_TRACEME {
+        current_task()
+            .unwrap()
+            .inner_exclusive_access()
+            .ptrace_traceme();
+        return 0;
+    }
+    let Some(task) = resolve_pid(pid) else {
+        return -1;
+    };
+    match request {
+        PTRACE_ATTACH => {
+            task.inner_exclusive_access().ptrace_attach();
+            0
+        }
+        PTRACE_DETACH => {
+            task.inner_exclusive_access().ptrace_detach();
+            0
+        }
+        PTRACE_CONT => {
+            task.inner_exclusive_access().ptrace_resume(false);
+            0
+        }
+        PTRACE_SINGLESTEP => {
+            task.inner_exclusive_access().ptrace_resume(true);
+            0
+        }
+        PTRACE_GETREGS => {
+            let (regs, sepc) = task.inner_exclusive_access().ptrace_getregs();
+            let mut buf = [0usize; 33];
+            buf[..32].copy_from_slice(&regs);
+            buf[32] = sepc;
+            let bytes = unsafe {
+                core::slice::from_raw_parts(
+                    buf.as_ptr() as *const u8,
+                    core::mem::size_of_val(&buf),
+                )
+            };
+            match copy_to_user(addr, bytes) {
+                Ok(_) => 0,
+                Err(_) => -1,
+            }
+        }
+        PTRACE_SETREGS => {
+            let mut buf = [0usize; 33];
+            let bytes = unsafe {
+                core::slice::from_raw_parts_mut(
+                    buf.as_mut_ptr() as *mut u8,
+                    core::mem::size_of_val(&buf),
+                )
+            };
+            if crate::task::copy_from_user(addr, bytes.len())
+                .map(|copied| bytes.copy_from_slice(&copied))
+                .is_err()
+            {
+                return -1;
+            }
+            let regs: [usize; 32] = buf[..32].try_into().unwrap();
+            task.inner_exclusive_access().ptrace_setregs(regs, buf[32]);
+            0
+        }
+        PTRACE_PEEKDATA => match task
+            .inner_exclusive_access()
+            .memory_set
+            .ptrace_peek(VirtAddr(addr))
+        {
+            Ok(v) => v as isize,
+            Err(_) => -1,
+        },
+        PTRACE_POKEDATA => match task
+            .inner_exclusive_access()
+            .memory_set
+            .ptrace_poke(VirtAddr(addr), data)
+        {
+            Ok(_) => 0,
+            Err(_) => -1,
+        },
+        _ => -1,
+    }
+}
+
+/// Open `path` with the given `OpenFlags` bits, installing a [`FileHandle`] in
+/// the calling task's `fd_table` and returning its fd, or `-1` if `path`
+/// doesn't resolve or `flags` isn't a valid `OpenFlags` bit pattern. This is
+/// what actually populates `fd_table` - without it, every fd `sys_lseek`/
+/// `sys_fcntl` could look up was permanently empty.
+pub fn sys_open(path: *const u8, flags: u32) -> isize {
+    trace!("kernel:pid[{}] sys_open", current_task().unwrap().pid.0);
+    let Some(open_flags) = OpenFlags::from_bits(flags) else {
+        return -1;
+    };
+    let token = current_user_token();
+    let path = translated_str(token, path);
+    let Some(inode) = open_file(path.as_str(), open_flags) else {
+        return -1;
+    };
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let fd = inner.alloc_fd();
+    inner.fd_table[fd] = Some(FileHandle::new(inode, flags));
+    fd as isize
+}
+
+/// `F_DUPFD` command recognised by [`sys_fcntl`]: duplicate an existing fd.
+const F_DUPFD: i32 = 0;
+/// `F_GETFL`/`F_SETFL` commands recognised by [`sys_fcntl`].
+const F_GETFL: i32 = 3;
+const F_SETFL: i32 = 4;
+
+/// Reposition `fd`'s cursor per `whence` (`0` = `Set`, `1` = `Cur`, `2` = `End`),
+/// returning the new absolute offset, or `-1` if `fd` isn't open or the result
+/// would be negative.
+pub fn sys_lseek(fd: usize, offset: isize, whence: usize) -> isize {
+    trace!("kernel:pid[{}] sys_lseek", current_task().unwrap().pid.0);
+    let whence = match whence {
+        0 => SeekWhence::Set,
+        1 => SeekWhence::Cur,
+        2 => SeekWhence::End,
+        _ => return -1,
+    };
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    match inner.fd_table.get_mut(fd) {
+        Some(Some(handle)) => handle.lseek(offset, whence).map_or(-1, |off| off as isize),
+        _ => -1,
+    }
+}
+
+/// `F_DUPFD` duplicates `fd` onto the lowest-numbered free descriptor, sharing
+/// the same underlying file (see [`FileHandle::inode`]) but with its own
+/// cursor and flags, seeded from `fd`'s current ones; `F_GETFL` returns `fd`'s
+/// current open flags; `F_SETFL` overwrites them with `arg` and returns `0`.
+/// Any other `cmd`, or a closed `fd`, fails with `-1`.
+pub fn sys_fcntl(fd: usize, cmd: usize, arg: i32) -> isize {
+    trace!("kernel:pid[{}] sys_fcntl", current_task().unwrap().pid.0);
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let Some(Some(handle)) = inner.fd_table.get_mut(fd) else {
+        return -1;
+    };
+    match cmd as i32 {
+        F_DUPFD => {
+            let inode = handle.inode();
+            let flags = handle.flags();
+            let offset = handle.lseek(0, SeekWhence::Cur).unwrap_or(0);
+            let new_fd = inner.alloc_fd();
+            let mut dup = FileHandle::new(inode, flags);
+            dup.lseek(offset as isize, SeekWhence::Set);
+            inner.fd_table[new_fd] = Some(dup);
+            new_fd as isize
+        }
+        F_GETFL => handle.flags() as isize,
+        F_SETFL => {
+            handle.set_flags(arg as u32);
+            0
+        }
+        _ => -1,
+    }
+}
+
 /// change data segment size
 pub fn sys_sbrk(size: i32) -> isize {
     trace!("kernel:pid[{}] sys_sbrk", current_task().unwrap().pid.0);
@@ -229,11 +478,61 @@ pub fn sys_spawn(path: *const u8) -> isize {
     let new_task = current.spawn(&app);
     let new_pid = new_task.pid.0;
 
+    // a sandboxed process must not be able to shed its seccomp filter just by spawning
+    inherit_seccomp(&current, &new_task);
+    // so ptrace can reach this child by pid
+    register_pid(&new_task);
     // add new task to scheduler
     add_task(new_task);
     new_pid as isize
 }
 
+/// Install a seccomp policy on the current task. `mode` packs two bits: bit 0
+/// selects `SeccompMode::Strict` (0) vs `SeccompMode::Filter` (1), bit 1 selects
+/// `SeccompAction::Deny` (0) vs `SeccompAction::Kill` (1). In `Filter` mode,
+/// `allow_list_ptr`/`len` point at a user-space array of `len` `usize` syscall
+/// numbers to allow (ignored in `Strict` mode) — a fixed-width bitmap can't
+/// represent this kernel's syscall numbers (up to `SYSCALL_TASK_INFO == 410`),
+/// so the allow-list has to be read from user memory instead. Returns `0` on
+/// success, `-1` if a policy is already installed and this one would not
+/// narrow it, or if the allow-list couldn't be read.
+pub fn sys_set_seccomp(mode: usize, allow_list_ptr: usize, len: usize) -> isize {
+    trace!(
+        "kernel:pid[{}] sys_set_seccomp",
+        current_task().unwrap().pid.0
+    );
+    let seccomp_mode = if mode & 0b01 == 0 {
+        SeccompMode::Strict
+    } else {
+        // An allow-list can never sensibly need more entries than there are
+        // syscall numbers; reject anything past that before it can overflow
+        // the byte-count multiplication below or force copy_from_user to
+        // allocate an unbounded Vec on an attacker-controlled length.
+        if len > MAX_SYSCALL_NUM {
+            return -1;
+        }
+        let bytes = match copy_from_user(allow_list_ptr, len * core::mem::size_of::<usize>()) {
+            Ok(bytes) => bytes,
+            Err(_) => return -1,
+        };
+        let allowed: BTreeSet<usize> = bytes
+            .chunks_exact(core::mem::size_of::<usize>())
+            .map(|chunk| usize::from_ne_bytes(chunk.try_into().unwrap()))
+            .collect();
+        SeccompMode::Filter(allowed)
+    };
+    let action = if mode & 0b10 == 0 {
+        SeccompAction::Deny
+    } else {
+        SeccompAction::Kill
+    };
+    if install_seccomp(seccomp_mode, action) {
+        0
+    } else {
+        -1
+    }
+}
+
 // YOUR JOB: Set task priority.
 pub fn sys_set_priority(prio: isize) -> isize {
     trace!(
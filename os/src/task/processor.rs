@@ -6,15 +6,167 @@
 
 use super::__switch;
 use super::{fetch_task, TaskStatus};
+use super::{SeccompAction, SeccompDecision, SeccompFilter, SeccompMode};
 use super::{TaskContext, TaskControlBlock};
+use super::{exit_current_and_run_next, BIG_STRIDE};
+use super::{Scheduler, Stride, StrideScheduler};
 use crate::config::{MAX_SYSCALL_NUM, PAGE_SIZE};
-use crate::mm::VirtAddr;
+use crate::mm::err::{AccessKind, MMError, MMResult, TranslateError, TranslateResult};
+use crate::mm::{MapPermission, VirtAddr};
 use crate::sync::UPSafeCell;
 use crate::timer::get_time_ms;
 use crate::trap::TrapContext;
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, VecDeque};
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use lazy_static::*;
 
+/// A pluggable ready-queue discipline that a `Processor` owns outright, so
+/// `run_tasks` defers all task-selection policy to it instead of hard-coding one
+/// scheduling class. Unlike task.rs's generic `Scheduler<T>`, this operates
+/// directly on `Arc<TaskControlBlock>` and reaches into `inner_exclusive_access()`
+/// for bookkeeping, matching the rest of this file's conventions.
+pub trait SchedulerPolicy {
+    /// Add a newly-ready task to this hart's local queue.
+    fn add(&mut self, task: Arc<TaskControlBlock>);
+    /// Remove and return the task that should run next, if any is ready.
+    fn next(&mut self) -> Option<Arc<TaskControlBlock>>;
+    /// Called from the timer trap handler on every tick with the currently
+    /// running task. Returns `true` if its time slice is up and it should be
+    /// preempted now.
+    fn on_tick(&mut self, task: &Arc<TaskControlBlock>) -> bool;
+}
+
+/// Plain FIFO: whichever task has been ready longest runs next, and every tick
+/// is a preemption point (round-robin with a one-tick time slice).
+pub struct FifoScheduler {
+    ready: VecDeque<Arc<TaskControlBlock>>,
+}
+
+impl FifoScheduler {
+    /// An empty FIFO ready queue.
+    pub fn new() -> Self {
+        Self {
+            ready: VecDeque::new(),
+        }
+    }
+}
+
+impl SchedulerPolicy for FifoScheduler {
+    fn add(&mut self, task: Arc<TaskControlBlock>) {
+        self.ready.push_back(task);
+    }
+    fn next(&mut self) -> Option<Arc<TaskControlBlock>> {
+        self.ready.pop_front()
+    }
+    fn on_tick(&mut self, _task: &Arc<TaskControlBlock>) -> bool {
+        true
+    }
+}
+
+/// FIFO ordering, but a dispatched task keeps the CPU for up to `time_slice`
+/// ticks before `on_tick` reports it as preemptable.
+pub struct RoundRobinScheduler {
+    ready: VecDeque<Arc<TaskControlBlock>>,
+    time_slice: usize,
+    remaining: usize,
+}
+
+impl RoundRobinScheduler {
+    /// A round-robin queue with the given time slice, in timer ticks.
+    pub fn new(time_slice: usize) -> Self {
+        Self {
+            ready: VecDeque::new(),
+            time_slice,
+            remaining: 0,
+        }
+    }
+}
+
+impl SchedulerPolicy for RoundRobinScheduler {
+    fn add(&mut self, task: Arc<TaskControlBlock>) {
+        self.ready.push_back(task);
+    }
+    fn next(&mut self) -> Option<Arc<TaskControlBlock>> {
+        self.remaining = self.time_slice;
+        self.ready.pop_front()
+    }
+    fn on_tick(&mut self, _task: &Arc<TaskControlBlock>) -> bool {
+        if self.remaining == 0 {
+            return true;
+        }
+        self.remaining -= 1;
+        self.remaining == 0
+    }
+}
+
+/// Default time slice for [`RoundRobinScheduler`], in timer ticks.
+const ROUND_ROBIN_TIME_SLICE: usize = 5;
+
+/// Lets task.rs's generic [`StrideScheduler`] operate on `Arc<TaskControlBlock>`
+/// directly, going through `inner_exclusive_access()` like the rest of this file,
+/// instead of `StrideSchedulerPolicy` reimplementing stride dispatch itself.
+impl Stride for Arc<TaskControlBlock> {
+    fn stride(&self) -> u64 {
+        self.inner_exclusive_access().stride
+    }
+    fn set_stride(&mut self, stride: u64) {
+        self.inner_exclusive_access().stride = stride;
+    }
+    fn priority(&self) -> usize {
+        self.inner_exclusive_access().priority
+    }
+}
+
+/// Stride scheduling: always dispatches the ready task with the smallest
+/// `stride`, then advances its stride by its own `pass = BIG_STRIDE / priority`.
+/// A thin `SchedulerPolicy` wrapper around task.rs's generic, wraparound-safe
+/// `StrideScheduler<T>` so there is exactly one stride-dispatch implementation.
+pub struct StrideSchedulerPolicy {
+    ready: StrideScheduler<Arc<TaskControlBlock>>,
+}
+
+impl StrideSchedulerPolicy {
+    /// An empty stride-scheduled ready queue.
+    pub fn new() -> Self {
+        Self {
+            ready: StrideScheduler::new(),
+        }
+    }
+}
+
+impl SchedulerPolicy for StrideSchedulerPolicy {
+    fn add(&mut self, task: Arc<TaskControlBlock>) {
+        self.ready.insert(task);
+    }
+    fn next(&mut self) -> Option<Arc<TaskControlBlock>> {
+        self.ready.pop()
+    }
+    fn on_tick(&mut self, _task: &Arc<TaskControlBlock>) -> bool {
+        true
+    }
+}
+
+/// Which [`SchedulerPolicy`] `Processor::new` installs. Selected at build time by
+/// Cargo feature (`sched-fifo` / `sched-rr`; stride scheduling is the default when
+/// neither is enabled), so the same kernel source can be built with a different
+/// scheduling class without touching this file.
+fn boot_policy() -> Box<dyn SchedulerPolicy> {
+    #[cfg(feature = "sched-fifo")]
+    {
+        Box::new(FifoScheduler::new())
+    }
+    #[cfg(feature = "sched-rr")]
+    {
+        Box::new(RoundRobinScheduler::new(ROUND_ROBIN_TIME_SLICE))
+    }
+    #[cfg(not(any(feature = "sched-fifo", feature = "sched-rr")))]
+    {
+        Box::new(StrideSchedulerPolicy::new())
+    }
+}
+
 /// Processor management structure
 pub struct Processor {
     ///The task currently executing on the current processor
@@ -22,6 +174,9 @@ pub struct Processor {
 
     ///The basic control flow of each core, helping to select and switch process
     idle_task_cx: TaskContext,
+
+    /// This hart's scheduling policy; see [`SchedulerPolicy`].
+    policy: Box<dyn SchedulerPolicy>,
 }
 
 impl Processor {
@@ -30,6 +185,7 @@ impl Processor {
         Self {
             current: None,
             idle_task_cx: TaskContext::zero_init(),
+            policy: boot_policy(),
         }
     }
 
@@ -49,50 +205,113 @@ impl Processor {
     }
 }
 
+/// Number of harts this kernel is built for. Boards with fewer harts just leave
+/// the unused `Processor` slots idle.
+const NCPU: usize = 4;
+
+/// This hart's id, as `entry.asm` left it in `tp` before any Rust code ran (it is
+/// never rewritten afterwards, so reading it back here is always safe).
+pub fn hart_id() -> usize {
+    let id: usize;
+    unsafe {
+        core::arch::asm!("mv {0}, tp", out(reg) id);
+    }
+    id
+}
+
 lazy_static! {
-    pub static ref PROCESSOR: UPSafeCell<Processor> = unsafe { UPSafeCell::new(Processor::new()) };
+    /// One `Processor` per hart, indexed by `hart_id()`. A hart only ever touches
+    /// its own slot (including its own `policy`), so the `UPSafeCell`s never see
+    /// cross-hart contention; the shared ready queue behind `fetch_task` is the
+    /// only state actually shared between harts, and it takes its own lock.
+    ///
+    /// Locking order: never hold a `Processor` guard while calling `fetch_task`.
+    /// `run_tasks` below always drains the shared queue (acquiring and releasing
+    /// its lock per call) *before* taking the local `Processor` guard to hand the
+    /// drained tasks to `policy`, and drops that guard again before the next loop
+    /// iteration touches the queue — so the two locks are never nested in either
+    /// order and harts can never deadlock against each other or themselves.
+    pub static ref PROCESSORS: [UPSafeCell<Processor>; NCPU] =
+        core::array::from_fn(|_| unsafe { UPSafeCell::new(Processor::new()) });
 }
 
-static BIG_STRIDE: usize = 0x100000;
+/// The calling hart's own `Processor`.
+fn this_processor() -> &'static UPSafeCell<Processor> {
+    &PROCESSORS[hart_id()]
+}
 
 ///The main part of process execution and scheduling
-///Loop `fetch_task` to get the process that needs to run, and switch the process through `__switch`
+///
+///Feeds every task drained from the shared ready queue into this hart's
+///`SchedulerPolicy`, asks it which one to run next, and `__switch`es to it. The
+///actual selection rule (FIFO, round-robin, stride, ...) lives entirely in the
+///policy `Processor` holds — see [`SchedulerPolicy`].
 pub fn run_tasks() {
     loop {
-        let mut processor = PROCESSOR.exclusive_access();
-        if let Some(task) = fetch_task() {
-            let idle_task_cx_ptr = processor.get_idle_task_cx_ptr();
-            // access coming task TCB exclusively
-            let mut task_inner = task.inner_exclusive_access();
-            let next_task_cx_ptr = &task_inner.task_cx as *const TaskContext;
-            task_inner.task_status = TaskStatus::Running;
-            if task_inner.first_run == 0 {
-                task_inner.first_run = get_time_ms();
+        let mut drained = Vec::new();
+        while let Some(task) = fetch_task() {
+            drained.push(task);
+        }
+
+        let mut processor = this_processor().exclusive_access();
+        for task in drained {
+            processor.policy.add(task);
+        }
+        let task = match processor.policy.next() {
+            Some(task) => task,
+            None => {
+                drop(processor);
+                warn!("no tasks available in run_tasks");
+                continue;
             }
-            task_inner.stride += BIG_STRIDE / task_inner.priority;
-            // release coming task_inner manually
+        };
+
+        let idle_task_cx_ptr = processor.get_idle_task_cx_ptr();
+        // access coming task TCB exclusively
+        let mut task_inner = task.inner_exclusive_access();
+        if task_inner.task_status == TaskStatus::Stopped {
+            // PTRACE_ATTACH landed on this task while it was only sitting in
+            // the ready queue, not yet running - dispatching it here would
+            // clobber the stop with Running before the tracee ever took a
+            // trap. Requeue it so it stays put until the tracer resumes it.
             drop(task_inner);
-            // release coming task TCB manually
-            processor.current = Some(task);
-            // release processor manually
+            processor.policy.add(task);
             drop(processor);
-            unsafe {
-                __switch(idle_task_cx_ptr, next_task_cx_ptr);
-            }
-        } else {
-            warn!("no tasks available in run_tasks");
+            continue;
+        }
+        let next_task_cx_ptr = &task_inner.task_cx as *const TaskContext;
+        task_inner.task_status = TaskStatus::Running;
+        if task_inner.first_run == 0 {
+            task_inner.first_run = get_time_ms();
+        }
+        // release coming task_inner manually
+        drop(task_inner);
+        // release coming task TCB manually
+        processor.current = Some(task);
+        // release processor manually
+        drop(processor);
+        unsafe {
+            __switch(idle_task_cx_ptr, next_task_cx_ptr);
         }
     }
 }
 
+/// Called from the timer trap handler on every tick: ask the current hart's
+/// scheduler policy whether the currently running task's time slice is up.
+/// Returns `true` if the trap handler should preempt (reschedule) now.
+pub fn scheduler_on_tick() -> bool {
+    let task = current_task().unwrap();
+    this_processor().exclusive_access().policy.on_tick(&task)
+}
+
 /// Get current task through take, leaving a None in its place
 pub fn take_current_task() -> Option<Arc<TaskControlBlock>> {
-    PROCESSOR.exclusive_access().take_current()
+    this_processor().exclusive_access().take_current()
 }
 
 /// Get a copy of the current task
 pub fn current_task() -> Option<Arc<TaskControlBlock>> {
-    PROCESSOR.exclusive_access().current()
+    this_processor().exclusive_access().current()
 }
 
 /// Get the current user token(addr of page table)
@@ -111,7 +330,7 @@ pub fn current_trap_cx() -> &'static mut TrapContext {
 
 ///Return to idle control flow for new scheduling
 pub fn schedule(switched_task_cx_ptr: *mut TaskContext) {
-    let mut processor = PROCESSOR.exclusive_access();
+    let mut processor = this_processor().exclusive_access();
     let idle_task_cx_ptr = processor.get_idle_task_cx_ptr();
     drop(processor);
     unsafe {
@@ -119,10 +338,66 @@ pub fn schedule(switched_task_cx_ptr: *mut TaskContext) {
     }
 }
 
-/// Record the syscall times of the current task.
-pub fn record_syscall(id: usize) {
+lazy_static! {
+    /// Per-task seccomp policies, keyed by `task_key` (the address of the task's
+    /// `Arc`). The TCB itself holds no seccomp state, so policies live in this
+    /// side table instead.
+    static ref SECCOMP_POLICIES: UPSafeCell<BTreeMap<usize, SeccompFilter>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
+}
+
+/// Identity key for `SECCOMP_POLICIES`: stable for as long as the `Arc` it was
+/// taken from (or any clone of it) stays alive.
+fn task_key(task: &Arc<TaskControlBlock>) -> usize {
+    Arc::as_ptr(task) as usize
+}
+
+/// Install a seccomp policy on the current task, backing a `sys_seccomp`-style
+/// syscall. Returns `false` (leaving the existing policy untouched) if a policy
+/// is already installed and `mode` would not narrow it.
+pub fn install_seccomp(mode: SeccompMode, action: SeccompAction) -> bool {
+    let key = task_key(&current_task().unwrap());
+    let mut policies = SECCOMP_POLICIES.exclusive_access();
+    if let Some(existing) = policies.get(&key) {
+        if !existing.narrows(&mode) {
+            return false;
+        }
+    }
+    policies.insert(key, SeccompFilter::new(mode, action));
+    true
+}
+
+/// Inherit `parent`'s seccomp policy into `child`, called from `fork`/`spawn` so
+/// a sandboxed process can't shed its filter just by spawning a child.
+pub fn inherit_seccomp(parent: &Arc<TaskControlBlock>, child: &Arc<TaskControlBlock>) {
+    let inherited = SECCOMP_POLICIES
+        .exclusive_access()
+        .get(&task_key(parent))
+        .cloned();
+    if let Some(filter) = inherited {
+        SECCOMP_POLICIES
+            .exclusive_access()
+            .insert(task_key(child), filter);
+    }
+}
+
+/// Record the syscall times of the current task, then consult its seccomp
+/// policy (if any) for what the dispatcher should do with `id`: run it as
+/// normal (`Allow`), fail it with `-EPERM` (`Deny`), or kill the task outright
+/// (`Kill`) before it ever reaches the syscall handler.
+pub fn record_syscall(id: usize) -> SeccompDecision {
     let current = current_task().unwrap();
     current.inner_exclusive_access().syscall_times[id] += 1;
+
+    let decision = SECCOMP_POLICIES
+        .exclusive_access()
+        .get(&task_key(&current))
+        .map_or(SeccompDecision::Allow, |filter| filter.decision(id));
+    if decision == SeccompDecision::Kill {
+        drop(current);
+        exit_current_and_run_next(-1);
+    }
+    decision
 }
 
 /// Get the current task's status, syscall times and first run time.
@@ -150,29 +425,158 @@ pub fn munmap(start: usize, len: usize) -> isize {
     current.memory_set.munmap(start.into(), len)
 }
 
-/// Copy data from kernel to user space
-pub fn copy_to_user(user: usize, kern: &[u8]) {
+/// Change the protection of an already-mapped range, e.g. to make a COW'd
+/// heap page read-only again or to mark a JIT buffer executable.
+pub fn mprotect(start: usize, len: usize, prot: usize) -> isize {
     let current = current_task().unwrap();
-    let current = current.inner_exclusive_access();
+    let mut current = current.inner_exclusive_access();
+    let mut perm = MapPermission::U;
+    if prot & 0b001 != 0 {
+        perm |= MapPermission::R;
+    }
+    if prot & 0b010 != 0 {
+        perm |= MapPermission::W;
+    }
+    if prot & 0b100 != 0 {
+        perm |= MapPermission::X;
+    }
+    let start: VirtAddr = start.into();
+    let end: VirtAddr = (start.0 + len).into();
+    match current.memory_set.mprotect(start, end, perm) {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
+
+/// A typed, fault-returning view onto the current task's user address space.
+/// Every method walks the range page-by-page, translating one page at a time so a
+/// value can straddle two non-contiguous physical frames, and fails with
+/// `TranslateError::NotMapped` on the first unmapped page (or, for a write, a page
+/// that is mapped but not writable) instead of panicking — so a syscall handler can
+/// turn a bad user pointer into `-EFAULT` rather than a kernel panic.
+pub trait MemoryAccessor {
+    /// Copy `kern` into user memory starting at `user`.
+    fn copy_to_user(&mut self, user: usize, kern: &[u8]) -> TranslateResult<()>;
+    /// Read `len` bytes of user memory starting at `user` into a freshly allocated `Vec`.
+    fn copy_from_user(&mut self, user: usize, len: usize) -> TranslateResult<Vec<u8>>;
+    /// Read a `Copy` value of type `T` out of user memory at `user`.
+    fn read_object<T: Copy>(&mut self, user: usize) -> TranslateResult<T>;
+    /// Write a `Copy` value of type `T` into user memory at `user`.
+    fn write_object<T: Copy>(&mut self, user: usize, val: &T) -> TranslateResult<()>;
+    /// Read a NUL-terminated string out of user memory, stopping at the first NUL
+    /// byte (not included in the result) or after `max_len` bytes, whichever comes
+    /// first — `Err(NotMapped)` if the scan runs off the end of mapped memory first.
+    fn read_c_string(&mut self, user: usize, max_len: usize) -> TranslateResult<Vec<u8>>;
+}
+
+impl MemoryAccessor for Processor {
+    fn copy_to_user(&mut self, user: usize, kern: &[u8]) -> TranslateResult<()> {
+        let task = self.current.as_ref().expect("no current task");
+        let mut inner = task.inner_exclusive_access();
+
+        let mut user_pos = user;
+        let mut need_copy = kern.len();
+        while need_copy > 0 {
+            let va = VirtAddr::from(user_pos);
+            let vpn = va.floor();
+            let vpoff = va.page_offset();
+
+            // A page can be mapped R|W by the area's permissions yet still be a
+            // read-only COW copy shared with the parent (e.g. a just-forked
+            // child's untouched buffer) - resolve that before translating, the
+            // same way BlockCopier::poll does for its destination page, so a
+            // legitimately-writable page doesn't spuriously fault as NotMapped.
+            let _ = inner.memory_set.handle_page_fault(vpn, AccessKind::Store);
+            let pte = inner
+                .memory_set
+                .translate(vpn)
+                .map_err(|_| TranslateError::NotMapped)?;
+            if !pte.writable() {
+                return Err(TranslateError::NotMapped);
+            }
+            let ppn = pte.ppn();
+            let dst = ppn.get_bytes_array()[vpoff..].as_mut();
+
+            let src = &kern[kern.len() - need_copy..];
+            let len = dst.len().min(need_copy).min(PAGE_SIZE - vpoff);
+            dst[..len].copy_from_slice(&src[..len]);
+
+            user_pos += len;
+            need_copy -= len;
+        }
+        Ok(())
+    }
 
-    let mut user_pos = user;
-    let mut need_copy = kern.len();
+    fn copy_from_user(&mut self, user: usize, len: usize) -> TranslateResult<Vec<u8>> {
+        let task = self.current.as_ref().expect("no current task");
+        let mut inner = task.inner_exclusive_access();
 
-    while need_copy > 0 {
-        let va = VirtAddr::from(user_pos);
-        let vpn = va.floor();
-        let vpoff = va.page_offset();
+        let mut out = Vec::with_capacity(len);
+        let mut user_pos = user;
+        let mut need_copy = len;
+        while need_copy > 0 {
+            let va = VirtAddr::from(user_pos);
+            let vpn = va.floor();
+            let vpoff = va.page_offset();
 
-        let pte = current.memory_set.translate(vpn).unwrap();
-        let ppn = pte.ppn();
-        let dst = ppn.get_bytes_array()[vpoff..].as_mut();
+            let pte = inner
+                .memory_set
+                .translate(vpn)
+                .map_err(|_| TranslateError::NotMapped)?;
+            let src = &pte.ppn().get_bytes_array()[vpoff..];
 
-        let src = &kern[kern.len() - need_copy..];
+            let len = src.len().min(need_copy).min(PAGE_SIZE - vpoff);
+            out.extend_from_slice(&src[..len]);
+
+            user_pos += len;
+            need_copy -= len;
+        }
+        Ok(out)
+    }
+
+    fn read_object<T: Copy>(&mut self, user: usize) -> TranslateResult<T> {
+        let bytes = self.copy_from_user(user, core::mem::size_of::<T>())?;
+        // SAFETY: `bytes` holds exactly `size_of::<T>()` freshly-copied bytes, and
+        // `T: Copy` rules out any `Drop` glue that reading it out-of-place would skip.
+        Ok(unsafe { core::ptr::read_unaligned(bytes.as_ptr() as *const T) })
+    }
 
-        let len = dst.len().min(need_copy).min(PAGE_SIZE - vpoff);
-        dst[..len].copy_from_slice(&src[..len]);
+    fn write_object<T: Copy>(&mut self, user: usize, val: &T) -> TranslateResult<()> {
+        let bytes = unsafe {
+            core::slice::from_raw_parts(val as *const T as *const u8, core::mem::size_of::<T>())
+        };
+        self.copy_to_user(user, bytes)
+    }
 
-        user_pos += len;
-        need_copy -= len;
+    fn read_c_string(&mut self, user: usize, max_len: usize) -> TranslateResult<Vec<u8>> {
+        let mut out = Vec::new();
+        for i in 0..max_len {
+            let byte: u8 = self.read_object(user + i)?;
+            if byte == 0 {
+                return Ok(out);
+            }
+            out.push(byte);
+        }
+        Ok(out)
     }
+}
+
+/// Copy `kern` into user memory starting at `user`. Thin wrapper over
+/// [`MemoryAccessor::copy_to_user`] on the current task's hart for callers that
+/// don't otherwise need a `Processor` handle.
+pub fn copy_to_user(user: usize, kern: &[u8]) -> MMResult<usize> {
+    this_processor()
+        .exclusive_access()
+        .copy_to_user(user, kern)
+        .map_err(MMError::from)?;
+    Ok(kern.len())
+}
+
+/// Symmetric counterpart to `copy_to_user`: read `len` bytes of user memory
+/// starting at `user` into a freshly allocated `Vec`.
+pub fn copy_from_user(user: usize, len: usize) -> MMResult<Vec<u8>> {
+    Ok(this_processor()
+        .exclusive_access()
+        .copy_from_user(user, len)
+        .map_err(MMError::from)?)
 }
\ No newline at end of file
@@ -1,13 +1,97 @@
 //! Types related to task management
-use alloc::collections::BTreeMap;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+
+use crate::fs::FileHandle;
 
 use super::TaskContext;
 use crate::config::TRAP_CONTEXT_BASE;
+use crate::mm::err::MMResult;
 use crate::mm::{
     kernel_stack_position, MapPermission, MemorySet, PhysPageNum, VirtAddr, KERNEL_SPACE,
 };
 use crate::trap::{trap_handler, TrapContext};
 
+/// Which syscalls a task may make under an installed [`SeccompFilter`].
+#[derive(Clone)]
+pub enum SeccompMode {
+    /// Only `read`/`write`/`exit`/`sched_yield` are permitted.
+    Strict,
+    /// Only syscall numbers present in the set are permitted.
+    Filter(BTreeSet<usize>),
+}
+
+/// What happens to a syscall `SeccompMode` doesn't permit.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SeccompAction {
+    /// The syscall fails with an `-EPERM`-style error; the task keeps running.
+    Deny,
+    /// The task is terminated immediately via `exit_current_and_run_next`.
+    Kill,
+}
+
+/// What the syscall dispatcher should do with a given syscall number, per the
+/// installed seccomp policy.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SeccompDecision {
+    /// No policy installed, or the syscall is permitted.
+    Allow,
+    /// Denied: dispatcher should return an `-EPERM`-style error without executing.
+    Deny,
+    /// Denied: dispatcher should kill the task without executing.
+    Kill,
+}
+
+/// An installed seccomp policy, sandboxing a task to a syscall allowlist.<br/>
+/// Once installed, a policy is irreversible: it can only be narrowed by installing a
+/// new `Filter` that is a subset of the current one, never widened, and `Strict`
+/// mode can never be replaced at all.
+#[derive(Clone)]
+pub struct SeccompFilter {
+    mode: SeccompMode,
+    action: SeccompAction,
+}
+
+impl SeccompFilter {
+    /// Syscall numbers permitted in `Strict` mode: read, write, exit, sched_yield.
+    const STRICT_ALLOWED: [usize; 4] = [63, 64, 93, 124];
+
+    /// Build a filter directly, e.g. for a lineage whose TCB has no `TaskInfoBlock`
+    /// to hang `install_seccomp` off of.
+    pub fn new(mode: SeccompMode, action: SeccompAction) -> Self {
+        Self { mode, action }
+    }
+
+    fn allows(&self, syscall_id: usize) -> bool {
+        match &self.mode {
+            SeccompMode::Strict => Self::STRICT_ALLOWED.contains(&syscall_id),
+            SeccompMode::Filter(allowed) => allowed.contains(&syscall_id),
+        }
+    }
+
+    /// Whether installing `new_mode` in place of this filter's mode would only
+    /// narrow (never widen) what's permitted — the one-way rule every policy
+    /// update must satisfy.
+    pub fn narrows(&self, new_mode: &SeccompMode) -> bool {
+        matches!(
+            (&self.mode, new_mode),
+            (SeccompMode::Filter(old), SeccompMode::Filter(new)) if new.is_subset(old)
+        )
+    }
+
+    /// What the syscall dispatcher should do with `syscall_id` under this policy.
+    pub fn decision(&self, syscall_id: usize) -> SeccompDecision {
+        if self.allows(syscall_id) {
+            SeccompDecision::Allow
+        } else {
+            match self.action {
+                SeccompAction::Deny => SeccompDecision::Deny,
+                SeccompAction::Kill => SeccompDecision::Kill,
+            }
+        }
+    }
+}
+
 /// Holds task info. <br/>
 pub struct TaskInfoBlock {
     /// Whether the task has already been dispatched
@@ -15,7 +99,7 @@ pub struct TaskInfoBlock {
     /// Timestamp in ms of the first time this task being dispatched
     pub dispatched_time: usize,
     /// Syscall times
-    pub syscall_times: BTreeMap<usize, u32>
+    pub syscall_times: BTreeMap<usize, u32>,
 }
 impl TaskInfoBlock {
     /// empty info block
@@ -23,7 +107,7 @@ impl TaskInfoBlock {
         TaskInfoBlock {
             dispatched: false,
             dispatched_time: 0,
-            syscall_times: BTreeMap::new()
+            syscall_times: BTreeMap::new(),
         }
     }
     /// Set the timestamp to now if it's the first to be dispatched
@@ -60,6 +144,30 @@ pub struct TaskControlBlock {
 
     /// Program break
     pub program_brk: usize,
+
+    /// Scheduling priority, set via `sys_set_priority`. Always `>= 2` so that
+    /// `BIG_STRIDE / priority` (the per-dispatch stride increment) never exceeds
+    /// `BIG_STRIDE / 2`.
+    pub priority: usize,
+
+    /// Stride-scheduling counter: advances by `BIG_STRIDE / priority` every time
+    /// this task is dispatched. The scheduler always runs whichever ready task has
+    /// the smallest `stride`.
+    pub stride: u64,
+
+    /// Whether a `ptrace` tracer is attached (via `PTRACE_TRACEME`/`PTRACE_ATTACH`).
+    /// `PTRACE_ATTACH` stops the task at once; otherwise a traced task stops
+    /// (`TaskStatus::Stopped`) after a syscall trap while `single_step` is set.
+    pub traced: bool,
+
+    /// Set by `PTRACE_TRACEME` and `PTRACE_SINGLESTEP` so the task stops again
+    /// after its next syscall trap; cleared once that stop happens. The stop
+    /// happens only at a syscall trap, not after a single instruction.
+    pub single_step: bool,
+
+    /// Open file descriptor table, indexed by fd. A `None` slot is a closed or
+    /// never-opened fd; `sys_lseek`/`sys_fcntl` look a `FileHandle` up here.
+    pub fd_table: Vec<Option<FileHandle>>,
 }
 
 impl TaskControlBlock {
@@ -99,6 +207,11 @@ impl TaskControlBlock {
             base_size: user_sp,
             heap_bottom: user_sp,
             program_brk: user_sp,
+            priority: 2,
+            stride: 0,
+            traced: false,
+            single_step: false,
+            fd_table: Vec::new(),
         };
         // prepare TrapContext in user space
         let trap_cx = task_control_block.get_trap_cx();
@@ -111,6 +224,109 @@ impl TaskControlBlock {
         );
         task_control_block
     }
+    /// Build a child task sharing this task's address space copy-on-write
+    /// (see [`MemorySet::clone_cow`]), for `fork`. The child gets its own
+    /// kernel stack at `app_id` and starts at the same `sepc`/registers as
+    /// the parent had at the time of the call; the caller is responsible for
+    /// overwriting the child's return value register once it has a pid to
+    /// report.
+    pub fn fork(&mut self, app_id: usize) -> MMResult<Self> {
+        let mut memory_set = self.memory_set.clone_cow()?;
+        let trap_cx_ppn = memory_set
+            .translate(VirtAddr::from(TRAP_CONTEXT_BASE).into())
+            .unwrap()
+            .ppn();
+        let (kernel_stack_bottom, kernel_stack_top) = kernel_stack_position(app_id);
+        let kernel_stack_alloc = KERNEL_SPACE.exclusive_access().insert_framed_area_strict(
+            kernel_stack_bottom.into(),
+            kernel_stack_top.into(),
+            MapPermission::R | MapPermission::W,
+        );
+        assert!(
+            kernel_stack_alloc.is_ok(),
+            "failed to allocate memory for kernel stack for appid = {}, err = {}",
+            app_id,
+            kernel_stack_alloc.err().unwrap()
+        );
+        let child = Self {
+            task_status: TaskStatus::Ready,
+            task_cx: TaskContext::goto_trap_return(kernel_stack_top),
+            task_info: TaskInfoBlock::new(),
+            memory_set,
+            trap_cx_ppn,
+            base_size: self.base_size,
+            heap_bottom: self.heap_bottom,
+            program_brk: self.program_brk,
+            priority: self.priority,
+            stride: 0,
+            traced: false,
+            single_step: false,
+            fd_table: self.fd_table.clone(),
+        };
+        let parent_cx = self.get_trap_cx();
+        let child_cx = child.get_trap_cx();
+        child_cx.x = parent_cx.x;
+        child_cx.sstatus = parent_cx.sstatus;
+        child_cx.sepc = parent_cx.sepc;
+        child_cx.kernel_satp = parent_cx.kernel_satp;
+        child_cx.kernel_sp = kernel_stack_top;
+        child_cx.trap_handler = parent_cx.trap_handler;
+        Ok(child)
+    }
+    /// Allocate the lowest-numbered free slot in `fd_table`, growing it if every
+    /// slot is already in use.
+    pub fn alloc_fd(&mut self) -> usize {
+        if let Some(fd) = self.fd_table.iter().position(|h| h.is_none()) {
+            fd
+        } else {
+            self.fd_table.push(None);
+            self.fd_table.len() - 1
+        }
+    }
+    /// `PTRACE_TRACEME`: mark this task as traced, so it stops after its next
+    /// syscall trap instead of resuming on its own.
+    pub fn ptrace_traceme(&mut self) {
+        self.traced = true;
+        self.single_step = true;
+    }
+    /// `PTRACE_ATTACH`: start tracing an already-running task and stop it
+    /// immediately so the tracer can inspect it.
+    pub fn ptrace_attach(&mut self) {
+        self.traced = true;
+        self.task_status = TaskStatus::Stopped;
+    }
+    /// `PTRACE_DETACH`: stop tracing and let the task run freely again.
+    pub fn ptrace_detach(&mut self) {
+        self.traced = false;
+        self.single_step = false;
+        if self.task_status == TaskStatus::Stopped {
+            self.task_status = TaskStatus::Ready;
+        }
+    }
+    /// `PTRACE_GETREGS`: snapshot this task's general-purpose registers and `sepc`.
+    pub fn ptrace_getregs(&self) -> ([usize; 32], usize) {
+        let cx = self.get_trap_cx();
+        (cx.x, cx.sepc)
+    }
+    /// `PTRACE_SETREGS`: overwrite this task's general-purpose registers and `sepc`.
+    pub fn ptrace_setregs(&self, regs: [usize; 32], sepc: usize) {
+        let cx = self.get_trap_cx();
+        cx.x = regs;
+        cx.sepc = sepc;
+    }
+    /// Enter `TaskStatus::Stopped` for the tracer. The scheduler skips stopped
+    /// tasks, and `sys_waitpid` must report this transition so the tracer can loop
+    /// until it chooses to resume the tracee.
+    pub fn ptrace_stop(&mut self) {
+        self.task_status = TaskStatus::Stopped;
+    }
+    /// `PTRACE_CONT`/`PTRACE_SINGLESTEP`: resume a stopped tracee. `single_step`
+    /// makes it stop again after its next syscall trap; it does not stop after a
+    /// single instruction.
+    pub fn ptrace_resume(&mut self, single_step: bool) {
+        self.task_status = TaskStatus::Ready;
+        self.single_step = single_step;
+    }
     /// change the location of the program break. return None if failed.
     pub fn change_program_brk(&mut self, size: i32) -> Option<usize> {
         let old_break = self.program_brk;
@@ -145,4 +361,183 @@ pub enum TaskStatus {
     Running,
     /// exited
     Exited,
+    /// stopped for a `ptrace` tracer; skipped by the scheduler until resumed
+    Stopped,
+}
+
+/// Large constant stride increments are measured against. With `pass = BIG_STRIDE /
+/// priority` and `priority >= 2` enforced by `sys_set_priority`, the largest possible
+/// `pass` is `BIG_STRIDE / 2`, which is what keeps the spread between any two ready
+/// tasks' strides bounded.
+pub const BIG_STRIDE: u64 = 0x10000;
+
+/// What a scheduler needs to know about a schedulable item to run stride scheduling,
+/// without otherwise caring what `T` is.
+pub trait Stride {
+    /// Current stride counter.
+    fn stride(&self) -> u64;
+    /// Overwrite the stride counter (used after advancing it by `pass`).
+    fn set_stride(&mut self, stride: u64);
+    /// Scheduling priority (`>= 2`).
+    fn priority(&self) -> usize;
+}
+
+impl Stride for TaskControlBlock {
+    fn stride(&self) -> u64 {
+        self.stride
+    }
+    fn set_stride(&mut self, stride: u64) {
+        self.stride = stride;
+    }
+    fn priority(&self) -> usize {
+        self.priority
+    }
+}
+
+/// Wrapping-safe stride comparison: true iff `a`'s stride is strictly ahead of
+/// `b`'s. With `priority >= 2` enforced by `sys_set_priority`, no task's `pass`
+/// (`BIG_STRIDE / priority`) exceeds `BIG_STRIDE / 2`, so the pairwise gap between
+/// any two ready tasks' strides never exceeds `BIG_STRIDE` and this signed-diff
+/// trick stays correct across wraparound.
+fn stride_after(a: u64, b: u64) -> bool {
+    (a.wrapping_sub(b) as i64) > 0
+}
+
+/// A pluggable ready-queue discipline, so the queue backing `add_task`/`fetch_task`
+/// can be swapped at build time while the rest of the kernel is unchanged.
+pub trait Scheduler<T> {
+    /// Insert a newly-ready task.
+    fn insert(&mut self, item: T);
+    /// Look at the task that would run next, without removing it.
+    fn peek(&self) -> Option<&T>;
+    /// Mutable access to the task that would run next.
+    fn peek_mut(&mut self) -> Option<&mut T>;
+    /// Remove and return the task that should run next.
+    fn pop(&mut self) -> Option<T>;
+    /// Remove a specific ready task (e.g. one that was just killed) matched by `pred`.
+    fn remove<F: FnMut(&T) -> bool>(&mut self, pred: F) -> Option<T>;
+}
+
+/// Stride-scheduling ready queue: always dispatches the ready task with the
+/// smallest `stride`, then advances that task's stride by its own
+/// `pass = BIG_STRIDE / priority` so a priority-4 task accumulates stride (and thus
+/// waits) about half as fast as a priority-2 task, i.e. gets roughly twice the CPU.
+pub struct StrideScheduler<T> {
+    ready: Vec<T>,
+}
+
+impl<T> StrideScheduler<T> {
+    /// An empty stride scheduler.
+    pub fn new() -> Self {
+        Self { ready: Vec::new() }
+    }
+}
+
+impl<T> StrideScheduler<T> {
+    /// Index of the ready task with the lowest stride, comparing wraparound-safely
+    /// via [`stride_after`] instead of a plain `min_by_key` (which breaks once any
+    /// task's stride has wrapped past `u64::MAX`).
+    fn min_idx(&self) -> Option<usize>
+    where
+        T: Stride,
+    {
+        if self.ready.is_empty() {
+            return None;
+        }
+        (1..self.ready.len()).fold(Some(0), |min_idx, i| {
+            let min_idx = min_idx?;
+            if stride_after(self.ready[min_idx].stride(), self.ready[i].stride()) {
+                Some(i)
+            } else {
+                Some(min_idx)
+            }
+        })
+    }
+}
+
+impl<T: Stride> Scheduler<T> for StrideScheduler<T> {
+    fn insert(&mut self, item: T) {
+        self.ready.push(item);
+    }
+
+    fn peek(&self) -> Option<&T> {
+        Some(&self.ready[self.min_idx()?])
+    }
+
+    fn peek_mut(&mut self) -> Option<&mut T> {
+        let idx = self.min_idx()?;
+        Some(&mut self.ready[idx])
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        let idx = self.min_idx()?;
+        let mut task = self.ready.remove(idx);
+        let pass = BIG_STRIDE / task.priority() as u64;
+        task.set_stride(task.stride().wrapping_add(pass));
+        Some(task)
+    }
+
+    fn remove<F: FnMut(&T) -> bool>(&mut self, mut pred: F) -> Option<T> {
+        let idx = self.ready.iter().position(|t| pred(t))?;
+        Some(self.ready.remove(idx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A schedulable item carrying just stride/priority, so stride fairness can
+    /// be tested without building a real `TaskControlBlock` (which needs a live
+    /// `MemorySet`/ELF image).
+    struct MockStride {
+        stride: u64,
+        priority: usize,
+    }
+
+    impl Stride for MockStride {
+        fn stride(&self) -> u64 {
+            self.stride
+        }
+        fn set_stride(&mut self, stride: u64) {
+            self.stride = stride;
+        }
+        fn priority(&self) -> usize {
+            self.priority
+        }
+    }
+
+    /// A priority-4 task should get roughly twice the CPU slices of a
+    /// priority-2 task: every time its stride catches back up, its `pass` is
+    /// half as large, so over many dispatch rounds it is picked about twice as
+    /// often.
+    #[test]
+    fn priority_four_gets_roughly_double_priority_two() {
+        let mut scheduler = StrideScheduler::new();
+        scheduler.insert(MockStride {
+            stride: 0,
+            priority: 2,
+        });
+        scheduler.insert(MockStride {
+            stride: 0,
+            priority: 4,
+        });
+
+        let mut picks = [0usize; 2]; // [priority2_count, priority4_count]
+        const ROUNDS: usize = 1000;
+        for _ in 0..ROUNDS {
+            let task = scheduler.pop().unwrap();
+            picks[if task.priority() == 2 { 0 } else { 1 }] += 1;
+            scheduler.insert(task);
+        }
+
+        let ratio = picks[1] as f64 / picks[0] as f64;
+        assert!(
+            (1.8..=2.2).contains(&ratio),
+            "expected priority 4 to be picked ~2x as often as priority 2, got {}/{} = {}",
+            picks[1],
+            picks[0],
+            ratio
+        );
+    }
 }
\ No newline at end of file
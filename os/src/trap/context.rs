@@ -0,0 +1,52 @@
+//! Trap context: the user-mode register snapshot saved/restored around every trap.
+use riscv::register::sstatus::{self, Sstatus, SPP};
+
+/// User-mode register snapshot, saved on trap entry and restored on trap return.
+/// Lives at the top of the task's trapframe page (`TRAP_CONTEXT_BASE`), which
+/// `__alltraps`/`__restore` read and write directly.
+#[repr(C)]
+pub struct TrapContext {
+    /// General-purpose registers x0..x31
+    pub x: [usize; 32],
+    /// Supervisor status register, saved so `__restore` can write it back
+    pub sstatus: Sstatus,
+    /// Supervisor exception program counter: where to resume user execution
+    pub sepc: usize,
+    /// Kernel address space token, for `__alltraps` to switch satp into
+    pub kernel_satp: usize,
+    /// Kernel stack pointer to switch to on trap entry
+    pub kernel_sp: usize,
+    /// Address of `trap_handler`, for `__alltraps` to jump to
+    pub trap_handler: usize,
+}
+
+impl TrapContext {
+    /// Overwrite the stack pointer (`x[2]`), e.g. after growing/moving the user stack.
+    pub fn set_sp(&mut self, sp: usize) {
+        self.x[2] = sp;
+    }
+    /// Build the initial trap context a freshly loaded (or forked+exec'd) task
+    /// resumes into: `sepc` at the ELF entry point, `sstatus.SPP` set to `User`
+    /// so `sret` drops privilege, and the kernel-side bookkeeping `__alltraps`
+    /// needs to get back into the kernel on the *next* trap.
+    pub fn app_init_context(
+        entry: usize,
+        sp: usize,
+        kernel_satp: usize,
+        kernel_sp: usize,
+        trap_handler: usize,
+    ) -> Self {
+        let mut sstatus = sstatus::read();
+        sstatus.set_spp(SPP::User);
+        let mut cx = Self {
+            x: [0; 32],
+            sstatus,
+            sepc: entry,
+            kernel_satp,
+            kernel_sp,
+            trap_handler,
+        };
+        cx.set_sp(sp);
+        cx
+    }
+}
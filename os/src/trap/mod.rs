@@ -0,0 +1,90 @@
+//! Trap handling: dispatches on `scause` to page faults, syscalls, and the
+//! supervisor timer interrupt, and hands control back to the scheduler.
+use riscv::register::{
+    scause::{self, Exception, Interrupt, Trap},
+    stval,
+};
+
+use crate::mm::err::AccessKind;
+use crate::mm::VirtAddr;
+use crate::task::{current_task, current_trap_cx, exit_current_and_run_next};
+
+pub use context::TrapContext;
+
+mod context;
+
+/// Entry point for all traps taken from user mode. Reads `scause`/`stval`,
+/// dispatches to the matching handler below, and returns (via `__restore`,
+/// not shown here) into the trap context it was called with.
+#[no_mangle]
+pub fn trap_handler() -> ! {
+    let scause = scause::read();
+    let stval = stval::read();
+    match scause.cause() {
+        Trap::Exception(Exception::UserEnvCall) => {
+            let cx = current_trap_cx();
+            cx.sepc += 4;
+            let result =
+                crate::syscall::syscall(cx.x[17], [cx.x[10], cx.x[11], cx.x[12], cx.x[13]]);
+            let cx = current_trap_cx();
+            cx.x[10] = result as usize;
+            maybe_ptrace_stop();
+        }
+        Trap::Exception(
+            e @ (Exception::StorePageFault
+            | Exception::LoadPageFault
+            | Exception::InstructionPageFault),
+        ) => {
+            let access = match e {
+                Exception::StorePageFault => AccessKind::Store,
+                Exception::LoadPageFault => AccessKind::Load,
+                _ => AccessKind::Instr,
+            };
+            let vpn = VirtAddr::from(stval).floor();
+            let task = current_task().unwrap();
+            let result = task
+                .inner_exclusive_access()
+                .memory_set
+                .handle_page_fault(vpn, access);
+            if result.is_err() {
+                // Not a recoverable lazy-page fault (no area covers it, or the
+                // access isn't permitted by the area) - kill the offending task.
+                drop(task);
+                exit_current_and_run_next(-2);
+            }
+        }
+        Trap::Interrupt(Interrupt::SupervisorTimer) => {
+            crate::timer::set_next_trigger();
+            // Ask this hart's scheduler policy whether the running task's slice
+            // is up; FIFO/round-robin/stride each decide this differently (see
+            // SchedulerPolicy::on_tick), so the trap handler itself stays policy-agnostic.
+            if crate::task::scheduler_on_tick() {
+                crate::task::suspend_current_and_run_next();
+            }
+        }
+        _ => {
+            panic!(
+                "Unsupported trap {:?}, stval = {:#x}!",
+                scause.cause(),
+                stval
+            );
+        }
+    }
+    unreachable!("trap_handler always returns to user mode via __restore");
+}
+
+/// If a `ptrace` tracer is attached and `single_step` is set on the current
+/// task, stop it now instead of letting it resume on its own. Called after each
+/// syscall, this is where `PTRACE_TRACEME` and `PTRACE_SINGLESTEP` take effect;
+/// page-fault and timer traps do not stop the tracee.
+fn maybe_ptrace_stop() {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    if inner.traced && inner.single_step {
+        inner.single_step = false;
+        inner.task_status = crate::task::TaskStatus::Stopped;
+        drop(inner);
+        drop(task);
+        crate::task::suspend_current_and_run_next();
+    }
+}